@@ -6,7 +6,7 @@ use std::rc::Rc;
 use std::mem;
 use std::fmt;
 
-use self::Inner::{Evaluated, EvaluationInProgress, Unevaluated, Redirect};
+use self::Inner::{Evaluated, EvaluationInProgress, Unevaluated, Redirect, Empty};
 
 /// A lazy, reference-counted value on the heap.
 pub struct Lazy<T> (UnsafeCell<Rc<UnsafeCell<Inner<T>>>>);
@@ -36,6 +36,81 @@ impl<T> Lazy<T> {
         Lazy(UnsafeCell::new(Rc::new(UnsafeCell::new(Evaluated(val)))))
     }
 
+    /// Create an empty `Lazy<T>` with no producer, to be assigned later via
+    /// `set` or `get_or_init`.
+    ///
+    /// Forcing (e.g. by deref) an empty `Lazy` that has not yet been given
+    /// a value panics, the same as forcing one recursively does.
+    pub fn empty() -> Lazy<T> {
+        Lazy(UnsafeCell::new(Rc::new(UnsafeCell::new(Empty))))
+    }
+
+    /// Assign the value of an empty `Lazy`, exactly once.
+    ///
+    /// Returns `Err(val)` without storing anything if this `Lazy` already
+    /// has a value, or is in the process of being produced. If this `Lazy`
+    /// was created via `Lazy::new` and its producer hasn't run yet, `set`
+    /// discards that producer without running it, instead of forcing it —
+    /// same as `get_or_init` does when it finds an unevaluated producer.
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let cell: Lazy<u32> = Lazy::empty();
+    /// assert_eq!(cell.set(7), Ok(()));
+    /// assert_eq!(cell.set(8), Err(8));
+    /// assert_eq!(*cell, 7u32);
+    /// ```
+    pub fn set(&self, val: T) -> Result<(), T> {
+        match *self.inner() {
+            Empty | Unevaluated(_) => {
+                *self.inner() = Evaluated(val);
+                Ok(())
+            }
+            _ => Err(val),
+        }
+    }
+
+    /// Return the value, producing it with `f` on the first call if this
+    /// `Lazy` does not have one yet. Later calls (from this or other
+    /// handles sharing the same `Lazy`) return the memoized value without
+    /// running `f` again.
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let cell: Lazy<u32> = Lazy::empty();
+    /// assert_eq!(*cell.get_or_init(|| 7), 7u32);
+    /// assert_eq!(*cell.get_or_init(|| 8), 7u32);
+    /// ```
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where F: FnOnce() -> T + 'static {
+        loop {
+            match *self.inner() {
+                Evaluated(_) => break,
+                Redirect(ref t) => {
+                    self.redirect(t.clone());
+                    continue;
+                }
+                EvaluationInProgress => {
+                    panic!("Lazy::get_or_init called recursively. (A Thunk tried to force itself while trying to force itself).")
+                }
+                Empty | Unevaluated(_) => {
+                    // Mark in-progress *before* running `f`, same as `force`
+                    // does for `Unevaluated`, so a reentrant call on a
+                    // shared clone panics instead of silently re-running
+                    // `f` and clobbering the value we're about to store.
+                    *self.inner() = EvaluationInProgress;
+                    let val = f();
+                    *self.inner() = Evaluated(val);
+                    break;
+                }
+            }
+        }
+        match *self.inner() {
+            Evaluated(ref val) => val,
+            _ => unreachable!(),
+        }
+    }
+
     /// Force evaluation of a `Lazy<T>`.
     ///
     /// You do not usually need to call this explicitly, as derefing calls `force`
@@ -52,7 +127,10 @@ impl<T> Lazy<T> {
                     self.redirect(t.clone());
                     continue;
                 },
-                Unevaluated(_) => ()
+                Unevaluated(_) => (),
+                Empty => {
+                    panic!("Lazy::force called on an empty Lazy with no value set. (Use Lazy::set or Lazy::get_or_init to give it one first.)")
+                }
             };
             break;
         }
@@ -84,7 +162,7 @@ impl<T> Lazy<T> {
         }
     }
 
-    fn rc(&self) -> &mut Rc<UnsafeCell<Inner<T>>> {
+    pub(crate) fn rc(&self) -> &mut Rc<UnsafeCell<Inner<T>>> {
         match *self {
             Lazy(ref cell) => unsafe {
                 &mut *cell.get()
@@ -95,6 +173,147 @@ impl<T> Lazy<T> {
     fn redirect(&self, t: Lazy<T>) {
         *self.rc() = t.rc().clone();
     }
+
+    /// Transform a `Lazy<T>` into a `Lazy<U>` without forcing `self`.
+    ///
+    /// `f` only runs when the result is forced, at which point it runs once
+    /// and the result is memoized, same as any other `Lazy`.
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let expensive: Lazy<u32> = Lazy::new(move || { println!("Evaluated!"); value(7) });
+    /// let doubled: Lazy<u32> = expensive.map(|x| x * 2);
+    /// assert_eq!(*doubled, 14u32); // "Evaluated!" gets printed here.
+    /// ```
+    pub fn map<U, F>(self, f: F) -> Lazy<U>
+    where
+        T: 'static,
+        U: 'static,
+        F: FnOnce(&T) -> U + 'static,
+    {
+        Lazy::new(move || value(f(&*self)))
+    }
+
+    /// Transform a `Lazy<T>` into a `Lazy<U>` via a function that itself
+    /// returns a `Lazy<U>`, without forcing `self`.
+    ///
+    /// This composes through the `Redirect` path, so the result shares the
+    /// `Lazy<U>` returned by `f` rather than copying its value.
+    ///
+    /// Chaining this many times and dropping the result *before forcing any
+    /// of it* will overflow the stack: until forced, the producer is an
+    /// opaque closure capturing `self`, so unwinding the chain on drop
+    /// recurses one stack frame per link (see `Drop for Lazy`). Forcing
+    /// such a chain recurses as well, since each link's producer derefs the
+    /// link before it, so a long chain overflows the stack either way.
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let a: Lazy<u32> = strict(7);
+    /// let b: Lazy<u32> = a.and_then(|x| strict(x * 2));
+    /// assert_eq!(*b, 14u32);
+    /// ```
+    pub fn and_then<U, F>(self, f: F) -> Lazy<U>
+    where
+        T: 'static,
+        U: 'static,
+        F: FnOnce(&T) -> Lazy<U> + 'static,
+    {
+        Lazy::new(move || redirect(f(&*self)))
+    }
+
+    /// Combine two `Lazy` values into a `Lazy` of their pair, without
+    /// forcing either until the result is forced.
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let a: Lazy<u32> = strict(7);
+    /// let b: Lazy<u32> = strict(8);
+    /// let pair: Lazy<(u32, u32)> = a.zip(b);
+    /// assert_eq!(*pair, (7, 8));
+    /// ```
+    pub fn zip<U>(self, other: Lazy<U>) -> Lazy<(T, U)>
+    where
+        T: Clone + 'static,
+        U: Clone + 'static,
+    {
+        Lazy::new(move || value(((*self).clone(), (*other).clone())))
+    }
+
+    /// Returns `true` if this `Lazy` has already been evaluated.
+    ///
+    /// Unlike `force`, this never runs the producer; it only follows
+    /// `Redirect` links (compressing them, same as `force` does) to see
+    /// whether the terminal node is `Evaluated`.
+    pub fn is_evaluated(&self) -> bool {
+        loop {
+            match *self.inner() {
+                Evaluated(_) => return true,
+                Redirect(ref t) => {
+                    self.redirect(t.clone());
+                    continue;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Returns the value if it has already been evaluated, without
+    /// triggering evaluation.
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let thunk: Lazy<u32> = Lazy::new(move || value(7));
+    /// assert_eq!(thunk.try_get(), None);
+    /// assert_eq!(*thunk, 7);
+    /// assert_eq!(thunk.try_get(), Some(&7));
+    /// ```
+    pub fn try_get(&self) -> Option<&T> {
+        loop {
+            match *self.inner() {
+                Evaluated(ref val) => return Some(val),
+                Redirect(ref t) => {
+                    self.redirect(t.clone());
+                    continue;
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Move the evaluated value out of this `Lazy`, if it is both evaluated
+    /// and this is the only remaining reference to it.
+    ///
+    /// Returns `None` without evaluating anything if the value is
+    /// unevaluated, and `None` without moving anything if the value is
+    /// evaluated but shared with other `Lazy` handles (clones).
+    ///
+    /// ```rust
+    /// # use lazy_ref::*;
+    /// let thunk: Lazy<String> = strict("hello".to_string());
+    /// assert_eq!(thunk.into_inner(), Some("hello".to_string()));
+    ///
+    /// let shared: Lazy<String> = strict("hello".to_string());
+    /// let other = shared.clone();
+    /// assert_eq!(shared.into_inner(), None);
+    /// assert_eq!(*other, "hello".to_string());
+    /// ```
+    pub fn into_inner(self) -> Option<T> {
+        if !self.is_evaluated() {
+            return None;
+        }
+        let rc = mem::replace(self.rc(), Rc::new(UnsafeCell::new(EvaluationInProgress)));
+        match Rc::try_unwrap(rc) {
+            Ok(cell) => match cell.into_inner() {
+                Evaluated(val) => Some(val),
+                _ => unsafe { debug_unreachable!() },
+            },
+            Err(rc) => {
+                *self.rc() = rc;
+                None
+            }
+        }
+    }
 }
 
 impl<T> Deref for Lazy<T> {
@@ -115,6 +334,44 @@ impl<T> Clone for Lazy<T> {
     }
 }
 
+impl<T> Drop for Lazy<T> {
+    /// Unlink long `Redirect` chains iteratively.
+    ///
+    /// Without this, dropping a `Lazy` that is the last reference to a
+    /// `Redirect` points at another `Lazy` that is in turn the last
+    /// reference to another `Redirect`, and so on: Rust's ordinary
+    /// (recursive) drop glue would walk the whole chain one stack frame
+    /// per link, and a long enough chain would overflow the stack. Instead
+    /// we detach each link's `Rc` by hand and loop, only following a link
+    /// when `Rc::try_unwrap` proves we are its last owner; as soon as a
+    /// link is still shared we stop and leave it (and everything after it)
+    /// intact for its own reference count to manage.
+    ///
+    /// This only covers chains that have already been forced into
+    /// `Redirect` links (e.g. via `force`, or `and_then` once its result
+    /// has been derefed). An `Unevaluated` producer is an opaque boxed
+    /// closure to us, so if it captures another `Lazy` (as `and_then`'s
+    /// does before it's forced), dropping it still recurses through
+    /// ordinary drop glue one stack frame per captured link. Forcing an
+    /// unforced `and_then` chain recurses once per link too, because each
+    /// producer derefs the `Lazy` it captured.
+    fn drop(&mut self) {
+        let mut rc = mem::replace(self.rc(), Rc::new(UnsafeCell::new(EvaluationInProgress)));
+        loop {
+            let cell = match Rc::try_unwrap(rc) {
+                Ok(cell) => cell,
+                Err(_) => break,
+            };
+            match cell.into_inner() {
+                Redirect(next) => {
+                    rc = mem::replace(next.rc(), Rc::new(UnsafeCell::new(EvaluationInProgress)));
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
 impl<T> fmt::Debug for Lazy<T>
     where T: fmt::Debug
 {
@@ -135,7 +392,7 @@ pub enum LazyResult<T> {
     Redirect(Lazy<T>)
 }
 
-struct Producer<T> {
+pub(crate) struct Producer<T> {
     inner: Box<Invoke<T>>
 }
 
@@ -160,11 +417,12 @@ impl<T> Producer<T> {
 }
 
 #[derive(Debug)]
-enum Inner<T> {
+pub(crate) enum Inner<T> {
     Evaluated(T),
     EvaluationInProgress,
     Unevaluated(Producer<LazyResult<T>>),
     Redirect(Lazy<T>),
+    Empty,
 }
 
 #[doc(hidden)]
@@ -299,3 +557,65 @@ pub fn value<T>(v: T) -> LazyResult<T> {
     LazyResult::Value(v)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process::Command;
+
+    // Building a long run of `lazy_redirect!` links, forcing each one as it
+    // is built, must drop without overflowing the stack. `force` compresses
+    // each redirect, so `chain` ends up sharing the first value's cell
+    // directly rather than holding a long chain of `Redirect` links. (An
+    // unforced chain of `Unevaluated` producers is a separate, documented
+    // limitation — see `Drop for Lazy`.)
+    #[test]
+    fn drop_long_forced_redirect_chain_does_not_overflow() {
+        let mut chain: Lazy<u32> = strict(0);
+        for _ in 0..200_000 {
+            let prev = chain;
+            chain = lazy_redirect! { prev };
+            chain.force();
+        }
+        assert_eq!(*chain, 0);
+        drop(chain);
+    }
+
+    const DEEP_CHAIN_ENV_VAR: &str = "LAZY_REF_TEST_DEEP_AND_THEN_DROP";
+
+    // Demonstrates the limitation documented on `and_then` and `Drop for
+    // Lazy`: a long `and_then` chain dropped before ever being forced
+    // still recurses through ordinary drop glue one stack frame per link,
+    // because each link's producer is an opaque closure capturing the
+    // previous `Lazy`. This really does crash the process, so the crash is
+    // driven in a child process and its (ab)normal exit is what's
+    // asserted on — that way a future fix has to update this test
+    // instead of the gap silently vanishing from coverage.
+    #[test]
+    fn drop_long_unforced_and_then_chain_overflows_stack() {
+        if env::var(DEEP_CHAIN_ENV_VAR).is_ok() {
+            let mut chain: Lazy<u32> = strict(0);
+            for _ in 0..200_000 {
+                chain = chain.and_then(|&x| strict(x));
+            }
+            drop(chain);
+            return;
+        }
+
+        let exe = env::current_exe().expect("could not determine test binary path");
+        let status = Command::new(exe)
+            .arg("--exact")
+            .arg("lazy::tests::drop_long_unforced_and_then_chain_overflows_stack")
+            .env(DEEP_CHAIN_ENV_VAR, "1")
+            .status()
+            .expect("failed to spawn child test process");
+        assert!(
+            !status.success(),
+            "expected dropping a long unforced and_then chain to crash the process; \
+             if this starts passing, the stack-overflow limitation documented on \
+             `Lazy::and_then` and `Drop for Lazy` has been fixed and this test (and \
+             those doc comments) should be updated instead of deleted"
+        );
+    }
+}
+
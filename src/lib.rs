@@ -0,0 +1,7 @@
+//! Lazy, memoized, reference-counted thunks, and data structures built on top of them.
+
+mod lazy;
+mod lazy_list;
+
+pub use lazy::*;
+pub use lazy_list::LazyList;